@@ -0,0 +1,78 @@
+// SPDX-License-Identifier: MIT OR BlueOak-1.0.0
+
+//! Structured parse errors with enough position information to point at
+//! the offending character.
+
+use std::fmt;
+
+/// The general category of a [`ParseError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// A character didn't belong at this point in the grammar.
+    UnexpectedChar,
+    /// The input ended in the middle of a value.
+    UnexpectedEof,
+    /// A number literal was malformed.
+    InvalidNumber,
+    /// A `\` escape in a string was malformed.
+    InvalidEscape,
+    /// Extra, non-whitespace input followed a complete top-level value.
+    TrailingData,
+    /// An object key appeared more than once under
+    /// [`DuplicateKeyPolicy::Error`](crate::DuplicateKeyPolicy::Error).
+    DuplicateKey,
+}
+
+/// A parse failure, carrying the byte offset, 1-based line/column, and a
+/// short "expected X, found Y" style message describing what went wrong.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub kind: ErrorKind,
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+    pub(crate) source_line: String,
+}
+
+impl ParseError {
+    pub(crate) fn new(
+        kind: ErrorKind,
+        offset: usize,
+        line: usize,
+        column: usize,
+        source_line: String,
+        message: impl Into<String>,
+    ) -> Self {
+        ParseError {
+            kind,
+            offset,
+            line,
+            column,
+            message: message.into(),
+            source_line,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} (line {}, column {})",
+            self.message, self.line, self.column
+        )?;
+        writeln!(f, "{}", self.source_line)?;
+        // `column` is 1-based and counts chars, not bytes; walk chars
+        // rather than indexing bytes so the caret lines up under the
+        // offending character even with multi-byte text before it.
+        let caret_offset = self
+            .source_line
+            .chars()
+            .take(self.column.saturating_sub(1))
+            .count();
+        write!(f, "{}^", " ".repeat(caret_offset))
+    }
+}
+
+impl std::error::Error for ParseError {}
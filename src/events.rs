@@ -0,0 +1,334 @@
+// SPDX-License-Identifier: MIT OR BlueOak-1.0.0
+
+//! SAX-style event emission for large documents.
+//!
+//! [`events`] scans JSON text and yields a stream of [`Event`]s instead of
+//! building an in-memory [`Value`](crate::Value) tree, so callers who only
+//! need to count records or pull out a couple of fields from a
+//! multi-gigabyte document can bail out early instead of paying to
+//! materialize the whole thing. Internally it drives the same `Cursor`
+//! and `parse_string`/`parse_number`/`parse_keyword` primitives as
+//! [`crate::parse`], but through an explicit stack of "in object
+//! expecting key / expecting value / in array" frames rather than
+//! recursive descent.
+
+use crate::error::{ErrorKind, ParseError};
+use crate::parse::{self, Cursor};
+use crate::value::Value;
+
+/// One token of a JSON document, in the order it was scanned.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    BeginObject,
+    Key(String),
+    BeginArray,
+    String(String),
+    Number(f64),
+    Bool(bool),
+    Null,
+    EndObject,
+    EndArray,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ObjectState {
+    /// Just after `{`: a key or a closing `}` may come next.
+    KeyOrEnd,
+    /// Just after `,`: only a key may come next.
+    Key,
+    /// Just after `key:`: a value is expected.
+    Value,
+    /// Just after a value: a `,` or closing `}` may come next.
+    Comma,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArrayState {
+    /// Just after `[`: a value or a closing `]` may come next.
+    ValueOrEnd,
+    /// Just after `,`: only a value may come next.
+    Value,
+    /// Just after a value: a `,` or closing `]` may come next.
+    Comma,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Frame {
+    Object(ObjectState),
+    Array(ArrayState),
+}
+
+/// Scans `s`, yielding a SAX-style [`Event`] stream instead of building a
+/// [`Value`](crate::Value) tree.
+pub fn events(s: &str) -> Events<'_> {
+    Events {
+        cursor: Cursor::new(s),
+        stack: Vec::new(),
+        started: false,
+        done: false,
+    }
+}
+
+/// Iterator returned by [`events`].
+pub struct Events<'a> {
+    cursor: Cursor<'a>,
+    stack: Vec<Frame>,
+    started: bool,
+    done: bool,
+}
+
+impl<'a> Events<'a> {
+    /// Marks that a value (scalar or a just-closed container) was
+    /// produced, advancing the enclosing frame (if any) to its
+    /// "expecting a comma or close" state.
+    fn after_value(&mut self) {
+        match self.stack.last_mut() {
+            Some(Frame::Object(state)) => *state = ObjectState::Comma,
+            Some(Frame::Array(state)) => *state = ArrayState::Comma,
+            None => {}
+        }
+    }
+
+    fn scalar(&mut self) -> Result<Event, ParseError> {
+        match self.cursor.peek() {
+            Some('"') => parse::parse_string(&mut self.cursor).map(Event::String),
+            Some('-') | Some('0'..='9') => {
+                parse::parse_number(&mut self.cursor).map(Event::Number)
+            }
+            Some('t') => parse::parse_keyword(&mut self.cursor, "true", Value::Bool(true))
+                .map(|_| Event::Bool(true)),
+            Some('f') => parse::parse_keyword(&mut self.cursor, "false", Value::Bool(false))
+                .map(|_| Event::Bool(false)),
+            Some('n') => {
+                parse::parse_keyword(&mut self.cursor, "null", Value::Null).map(|_| Event::Null)
+            }
+            Some(c) => Err(self.cursor.error(
+                ErrorKind::UnexpectedChar,
+                format!("unexpected character {:?}", c),
+            )),
+            None => Err(self.cursor.eof_error("expected a value")),
+        }
+    }
+
+    /// Scans whatever comes next where a value is expected: a nested
+    /// container's opening token, or a complete scalar.
+    fn value_event(&mut self) -> Result<Event, ParseError> {
+        match self.cursor.peek() {
+            Some('{') => {
+                self.cursor.next();
+                self.stack.push(Frame::Object(ObjectState::KeyOrEnd));
+                Ok(Event::BeginObject)
+            }
+            Some('[') => {
+                self.cursor.next();
+                self.stack.push(Frame::Array(ArrayState::ValueOrEnd));
+                Ok(Event::BeginArray)
+            }
+            _ => {
+                let event = self.scalar()?;
+                self.after_value();
+                Ok(event)
+            }
+        }
+    }
+
+    fn object_key(&mut self) -> Result<Event, ParseError> {
+        let key = parse::parse_string(&mut self.cursor)?;
+        parse::skip_ws(&mut self.cursor);
+        parse::expect(&mut self.cursor, ':')?;
+        parse::skip_ws(&mut self.cursor);
+        if let Some(Frame::Object(state)) = self.stack.last_mut() {
+            *state = ObjectState::Value;
+        }
+        Ok(Event::Key(key))
+    }
+
+    /// Advances the state stack by one event once at least one frame is
+    /// open (i.e. we're inside an object or array).
+    fn advance(&mut self) -> Result<Event, ParseError> {
+        loop {
+            parse::skip_ws(&mut self.cursor);
+            let frame = match self.stack.last() {
+                Some(f) => *f,
+                None => return Err(self.cursor.eof_error("no more structure to scan")),
+            };
+            match frame {
+                Frame::Object(ObjectState::KeyOrEnd) => {
+                    if self.cursor.peek() == Some('}') {
+                        self.cursor.next();
+                        self.stack.pop();
+                        self.after_value();
+                        return Ok(Event::EndObject);
+                    }
+                    return self.object_key();
+                }
+                Frame::Object(ObjectState::Key) => return self.object_key(),
+                Frame::Object(ObjectState::Value) => return self.value_event(),
+                Frame::Object(ObjectState::Comma) => match self.cursor.peek() {
+                    Some(',') => {
+                        self.cursor.next();
+                        if let Some(Frame::Object(state)) = self.stack.last_mut() {
+                            *state = ObjectState::Key;
+                        }
+                        continue;
+                    }
+                    Some('}') => {
+                        self.cursor.next();
+                        self.stack.pop();
+                        self.after_value();
+                        return Ok(Event::EndObject);
+                    }
+                    Some(c) => {
+                        return Err(self.cursor.error(
+                            ErrorKind::UnexpectedChar,
+                            format!("expected ',' or '}}', found {:?}", c),
+                        ))
+                    }
+                    None => {
+                        return Err(self.cursor.eof_error("expected ',' or '}' before end of input"))
+                    }
+                },
+                Frame::Array(ArrayState::ValueOrEnd) => {
+                    if self.cursor.peek() == Some(']') {
+                        self.cursor.next();
+                        self.stack.pop();
+                        self.after_value();
+                        return Ok(Event::EndArray);
+                    }
+                    return self.value_event();
+                }
+                Frame::Array(ArrayState::Value) => return self.value_event(),
+                Frame::Array(ArrayState::Comma) => match self.cursor.peek() {
+                    Some(',') => {
+                        self.cursor.next();
+                        if let Some(Frame::Array(state)) = self.stack.last_mut() {
+                            *state = ArrayState::Value;
+                        }
+                        continue;
+                    }
+                    Some(']') => {
+                        self.cursor.next();
+                        self.stack.pop();
+                        self.after_value();
+                        return Ok(Event::EndArray);
+                    }
+                    Some(c) => {
+                        return Err(self.cursor.error(
+                            ErrorKind::UnexpectedChar,
+                            format!("expected ',' or ']', found {:?}", c),
+                        ))
+                    }
+                    None => {
+                        return Err(self.cursor.eof_error("expected ',' or ']' before end of input"))
+                    }
+                },
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for Events<'a> {
+    type Item = Result<Event, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        parse::skip_ws(&mut self.cursor);
+
+        let result = if self.stack.is_empty() {
+            if !self.started {
+                self.started = true;
+                self.value_event()
+            } else {
+                // The top-level value already closed; anything left is
+                // trailing garbage.
+                return match self.cursor.peek() {
+                    None => {
+                        self.done = true;
+                        None
+                    }
+                    Some(c) => {
+                        self.done = true;
+                        Some(Err(self.cursor.error(
+                            ErrorKind::TrailingData,
+                            format!("unexpected trailing character {:?}", c),
+                        )))
+                    }
+                };
+            }
+        } else {
+            self.advance()
+        };
+
+        if result.is_err() {
+            self.done = true;
+        }
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collect(s: &str) -> Result<Vec<Event>, ParseError> {
+        events(s).collect()
+    }
+
+    #[test]
+    fn scalar_document() {
+        assert_eq!(collect("42").unwrap(), vec![Event::Number(42.0)]);
+    }
+
+    #[test]
+    fn nested_document() {
+        let got = collect(r#"{"a":[1,"two",null,true],"b":{}}"#).unwrap();
+        assert_eq!(
+            got,
+            vec![
+                Event::BeginObject,
+                Event::Key("a".to_string()),
+                Event::BeginArray,
+                Event::Number(1.0),
+                Event::String("two".to_string()),
+                Event::Null,
+                Event::Bool(true),
+                Event::EndArray,
+                Event::Key("b".to_string()),
+                Event::BeginObject,
+                Event::EndObject,
+                Event::EndObject,
+            ]
+        );
+    }
+
+    #[test]
+    fn stops_after_the_first_error() {
+        let mut it = events("[1, ]");
+        assert_eq!(it.next(), Some(Ok(Event::BeginArray)));
+        assert_eq!(it.next(), Some(Ok(Event::Number(1.0))));
+        assert!(matches!(it.next(), Some(Err(_))));
+        // Once an error is yielded, the iterator is done rather than
+        // continuing to scan from wherever the cursor was left.
+        assert_eq!(it.next(), None);
+    }
+
+    #[test]
+    fn trailing_data_after_a_complete_value_is_an_error() {
+        let err = collect("1 2").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::TrailingData);
+    }
+
+    #[test]
+    fn malformed_object_reports_unexpected_char() {
+        let err = collect(r#"{"a" 1}"#).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::UnexpectedChar);
+    }
+
+    #[test]
+    fn empty_input_is_an_eof_error() {
+        let err = collect("   ").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,17 @@
+// SPDX-License-Identifier: MIT OR BlueOak-1.0.0
+
+mod error;
+mod events;
+mod options;
+mod parse;
+mod query;
+mod stream;
+mod unicode;
+mod value;
+
+pub use error::{ErrorKind, ParseError};
+pub use events::{events, Event, Events};
+pub use options::{DuplicateKeyPolicy, NumberPolicy, ParseOptions};
+pub use parse::{parse, parse_with};
+pub use stream::{parse_many, parse_stream, parse_stream_str, Parser, Status};
+pub use value::Value;
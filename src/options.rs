@@ -0,0 +1,62 @@
+// SPDX-License-Identifier: MIT OR BlueOak-1.0.0
+
+//! Options controlling how [`crate::parse_with`] handles duplicate object
+//! keys and number literals.
+
+/// How to handle an object with more than one entry sharing the same key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateKeyPolicy {
+    /// Keep the last value seen for a key, discarding earlier ones. This
+    /// is what [`crate::parse`] does.
+    #[default]
+    LastWins,
+    /// Keep the first value seen for a key, discarding later ones.
+    FirstWins,
+    /// Fail to parse if a key appears more than once.
+    Error,
+    /// Keep every entry, in original order, as a
+    /// [`Value::OrderedObject`](crate::Value::OrderedObject) instead of
+    /// collapsing the object into a `HashMap`.
+    KeepAll,
+}
+
+/// How to represent a parsed JSON number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NumberPolicy {
+    /// Parse into an `f64`, as [`crate::parse`] does. Integers beyond
+    /// 2^53 lose precision.
+    #[default]
+    F64,
+    /// Preserve the original lexeme as a
+    /// [`Value::RawNumber`](crate::Value::RawNumber) string, so large
+    /// integers round-trip exactly.
+    Raw,
+}
+
+/// Options for [`crate::parse_with`], covering duplicate-key and number
+/// handling that [`crate::parse`]'s fixed behavior can't express.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ParseOptions {
+    pub(crate) duplicate_keys: DuplicateKeyPolicy,
+    pub(crate) numbers: NumberPolicy,
+}
+
+impl ParseOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the duplicate-key policy. Defaults to
+    /// [`DuplicateKeyPolicy::LastWins`].
+    pub fn duplicate_keys(mut self, policy: DuplicateKeyPolicy) -> Self {
+        self.duplicate_keys = policy;
+        self
+    }
+
+    /// Sets the number representation policy. Defaults to
+    /// [`NumberPolicy::F64`].
+    pub fn numbers(mut self, policy: NumberPolicy) -> Self {
+        self.numbers = policy;
+        self
+    }
+}
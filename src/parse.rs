@@ -1,5 +1,7 @@
 // SPDX-License-Identifier: MIT OR BlueOak-1.0.0
 
+use crate::error::{ErrorKind, ParseError};
+use crate::options::{DuplicateKeyPolicy, NumberPolicy, ParseOptions};
 use crate::unicode;
 use crate::value::Value;
 use std::collections::HashMap;
@@ -10,105 +12,262 @@ fn is_ws(c: char) -> bool {
     c == ' ' || c == '\t' || c == '\n' || c == '\r'
 }
 
-fn peek(chars: &Chars) -> Option<char> {
-    chars.clone().next()
+/// A parsing cursor over a `&str`, tracking byte offset and 1-based
+/// line/column alongside the underlying `Chars` so that failures can
+/// report where they happened.
+///
+/// Shared with [`crate::events`], which drives this same cursor and the
+/// `parse_string`/`parse_number`/`parse_keyword` primitives through an
+/// explicit state stack instead of recursive descent.
+#[derive(Clone)]
+pub(crate) struct Cursor<'a> {
+    root: &'a str,
+    chars: Chars<'a>,
+    offset: usize,
+    line: usize,
+    column: usize,
+    line_start: usize,
 }
 
-fn next_if(chars: &mut Chars, func: impl FnOnce(char) -> bool) -> Option<char> {
-    match peek(chars) {
-        Some(ch) if func(ch) => Some(chars.next().unwrap()),
+impl<'a> Cursor<'a> {
+    pub(crate) fn new(s: &'a str) -> Self {
+        Cursor {
+            root: s,
+            chars: s.chars(),
+            offset: 0,
+            line: 1,
+            column: 1,
+            line_start: 0,
+        }
+    }
+
+    pub(crate) fn peek(&self) -> Option<char> {
+        self.chars.clone().next()
+    }
+
+    pub(crate) fn next(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        self.offset += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+            self.line_start = self.offset;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn current_line(&self) -> &'a str {
+        let rest = &self.root[self.line_start..];
+        rest.split('\n').next().unwrap_or(rest)
+    }
+
+    pub(crate) fn error(&self, kind: ErrorKind, message: impl Into<String>) -> ParseError {
+        ParseError::new(
+            kind,
+            self.offset,
+            self.line,
+            self.column,
+            self.current_line().to_string(),
+            message,
+        )
+    }
+
+    pub(crate) fn eof_error(&self, message: impl Into<String>) -> ParseError {
+        self.error(ErrorKind::UnexpectedEof, message)
+    }
+}
+
+fn next_if(cursor: &mut Cursor, func: impl FnOnce(char) -> bool) -> Option<char> {
+    match cursor.peek() {
+        Some(ch) if func(ch) => Some(cursor.next().unwrap()),
         _ => None,
     }
 }
 
-fn skip_while(chars: &mut Chars, func: impl Fn(char) -> bool) {
-    while next_if(chars, |c| func(c)).is_some() {}
+fn skip_while(cursor: &mut Cursor, func: impl Fn(char) -> bool) {
+    while next_if(cursor, |c| func(c)).is_some() {}
 }
 
-fn consume_object_entry(chars: &mut Chars, out: &mut HashMap<String, Value>) -> Option<()> {
-    let key = parse_string(chars)?;
-    skip_while(chars, is_ws);
-    if chars.next()? != ':' {
-        return None;
+pub(crate) fn skip_ws(cursor: &mut Cursor) {
+    skip_while(cursor, is_ws)
+}
+
+pub(crate) fn expect(cursor: &mut Cursor, expected: char) -> Result<(), ParseError> {
+    match cursor.peek() {
+        Some(c) if c == expected => {
+            cursor.next();
+            Ok(())
+        }
+        Some(c) => Err(cursor.error(
+            ErrorKind::UnexpectedChar,
+            format!("expected {:?}, found {:?}", expected, c),
+        )),
+        None => Err(cursor.eof_error(format!("expected {:?}, found end of input", expected))),
     }
-    skip_while(chars, is_ws);
-    let val = parse_value(chars)?;
-    out.insert(key, val);
-    Some(())
 }
 
-fn parse_object(input: &mut Chars) -> Option<HashMap<String, Value>> {
-    let mut chars = input.clone();
-    if chars.next()? != '{' {
-        return None;
+/// Accumulates an object's entries under a [`DuplicateKeyPolicy`], either
+/// into a `HashMap` or, for [`DuplicateKeyPolicy::KeepAll`], into an
+/// order- and duplicate-preserving `Vec`.
+enum ObjectBuilder {
+    Map(HashMap<String, Value>),
+    Keep(Vec<(String, Value)>),
+}
+
+impl ObjectBuilder {
+    fn new(opts: &ParseOptions) -> Self {
+        match opts.duplicate_keys {
+            DuplicateKeyPolicy::KeepAll => ObjectBuilder::Keep(Vec::new()),
+            _ => ObjectBuilder::Map(HashMap::new()),
+        }
+    }
+
+    /// `key_site` is the cursor position just after the key was parsed,
+    /// used to report a duplicate-key error at the key rather than at
+    /// wherever the cursor happens to be once its value finishes parsing.
+    fn insert(
+        &mut self,
+        key_site: &Cursor,
+        key: String,
+        value: Value,
+        opts: &ParseOptions,
+    ) -> Result<(), ParseError> {
+        match self {
+            ObjectBuilder::Keep(entries) => {
+                entries.push((key, value));
+                Ok(())
+            }
+            ObjectBuilder::Map(map) => match opts.duplicate_keys {
+                DuplicateKeyPolicy::FirstWins => {
+                    map.entry(key).or_insert(value);
+                    Ok(())
+                }
+                DuplicateKeyPolicy::Error if map.contains_key(&key) => Err(key_site.error(
+                    ErrorKind::DuplicateKey,
+                    format!("duplicate key {:?}", key),
+                )),
+                _ => {
+                    map.insert(key, value);
+                    Ok(())
+                }
+            },
+        }
     }
-    skip_while(&mut chars, is_ws);
 
-    let mut out = HashMap::new();
-    if peek(&chars)? == '}' {
-        chars.next();
-        *input = chars;
-        return Some(out);
+    fn finish(self) -> Value {
+        match self {
+            ObjectBuilder::Map(map) => Value::Object(map),
+            ObjectBuilder::Keep(entries) => Value::OrderedObject(entries),
+        }
     }
-    consume_object_entry(&mut chars, &mut out)?;
-    skip_while(&mut chars, is_ws);
+}
+
+fn consume_object_entry(
+    cursor: &mut Cursor,
+    builder: &mut ObjectBuilder,
+    opts: &ParseOptions,
+) -> Result<(), ParseError> {
+    let key = parse_string(cursor)?;
+    let key_site = cursor.clone();
+    skip_while(cursor, is_ws);
+    expect(cursor, ':')?;
+    skip_while(cursor, is_ws);
+    let val = parse_value(cursor, opts)?;
+    builder.insert(&key_site, key, val, opts)
+}
+
+fn parse_object(cursor: &mut Cursor, opts: &ParseOptions) -> Result<Value, ParseError> {
+    expect(cursor, '{')?;
+    skip_while(cursor, is_ws);
+
+    let mut builder = ObjectBuilder::new(opts);
+    if cursor.peek() == Some('}') {
+        cursor.next();
+        return Ok(builder.finish());
+    }
+    consume_object_entry(cursor, &mut builder, opts)?;
+    skip_while(cursor, is_ws);
 
     loop {
-        match chars.next()? {
-            '}' => {
-                *input = chars;
-                return Some(out);
+        match cursor.peek() {
+            Some('}') => {
+                cursor.next();
+                return Ok(builder.finish());
             }
-            ',' => (),
-            _ => return None,
+            Some(',') => {
+                cursor.next();
+            }
+            Some(c) => {
+                return Err(cursor.error(
+                    ErrorKind::UnexpectedChar,
+                    format!("expected ',' or '}}', found {:?}", c),
+                ))
+            }
+            None => return Err(cursor.eof_error("expected ',' or '}' before end of input")),
         }
-        skip_while(&mut chars, is_ws);
+        skip_while(cursor, is_ws);
 
-        consume_object_entry(&mut chars, &mut out)?;
-        skip_while(&mut chars, is_ws);
+        consume_object_entry(cursor, &mut builder, opts)?;
+        skip_while(cursor, is_ws);
     }
 }
 
-fn parse_array(chars: &mut Chars) -> Option<Vec<Value>> {
-    if chars.next()? != '[' {
-        return None;
-    }
-    skip_while(chars, is_ws);
+fn parse_array(cursor: &mut Cursor, opts: &ParseOptions) -> Result<Vec<Value>, ParseError> {
+    expect(cursor, '[')?;
+    skip_while(cursor, is_ws);
     let mut out = Vec::new();
 
-    if peek(chars)? == ']' {
-        chars.next();
-        return Some(out);
+    if cursor.peek() == Some(']') {
+        cursor.next();
+        return Ok(out);
     }
 
-    out.push(parse_value(chars)?);
-    skip_while(chars, is_ws);
+    out.push(parse_value(cursor, opts)?);
+    skip_while(cursor, is_ws);
 
     loop {
-        match chars.next()? {
-            ']' => return Some(out),
-            ',' => (),
-            _ => return None,
+        match cursor.peek() {
+            Some(']') => {
+                cursor.next();
+                return Ok(out);
+            }
+            Some(',') => {
+                cursor.next();
+            }
+            Some(c) => {
+                return Err(cursor.error(
+                    ErrorKind::UnexpectedChar,
+                    format!("expected ',' or ']', found {:?}", c),
+                ))
+            }
+            None => return Err(cursor.eof_error("expected ',' or ']' before end of input")),
         }
-        skip_while(chars, is_ws);
-        out.push(parse_value(chars)?);
-        skip_while(chars, is_ws);
+        skip_while(cursor, is_ws);
+        out.push(parse_value(cursor, opts)?);
+        skip_while(cursor, is_ws);
     }
 }
 
-fn parse_four_hex(chars: &mut Chars) -> Option<u16> {
+fn parse_four_hex(cursor: &mut Cursor) -> Result<u16, ParseError> {
     let mut tmp = String::new();
-    tmp.push(chars.next()?);
-    tmp.push(chars.next()?);
-    tmp.push(chars.next()?);
-    tmp.push(chars.next()?);
-    u16::from_str_radix(&tmp, 16).ok()
+    for _ in 0..4 {
+        match cursor.next() {
+            Some(c) => tmp.push(c),
+            None => return Err(cursor.eof_error("expected 4 hex digits in unicode escape")),
+        }
+    }
+    u16::from_str_radix(&tmp, 16).map_err(|_| {
+        cursor.error(
+            ErrorKind::InvalidEscape,
+            format!("invalid unicode escape {:?}", tmp),
+        )
+    })
 }
 
-fn parse_string(chars: &mut Chars) -> Option<String> {
-    if chars.next()? != '"' {
-        return None;
-    }
+pub(crate) fn parse_string(cursor: &mut Cursor) -> Result<String, ParseError> {
+    expect(cursor, '"')?;
     let mut out = String::new();
     let mut pending = None;
 
@@ -130,123 +289,268 @@ fn parse_string(chars: &mut Chars) -> Option<String> {
     }
 
     loop {
-        match chars.next()? {
-            '"' => {
+        // Snapshot before consuming the next char so that any error built
+        // from this char points at it, not at whatever follows it.
+        let char_site = cursor.clone();
+        match cursor.next() {
+            Some('"') => {
                 flush_pending!();
-                return Some(out);
+                return Ok(out);
             }
-            '\\' => match chars.next()? {
-                '"' => push!('"'),
-                '\\' => push!('\\'),
-                '/' => push!('/'),
-                'b' => push!('\x08'),
-                'f' => push!('\x0c'),
-                'n' => push!('\n'),
-                'r' => push!('\r'),
-                't' => push!('\t'),
-                'u' => {
-                    let cu = parse_four_hex(chars)?;
-                    if let Some(ch) = char::from_u32(cu as u32) {
-                        push!(ch);
-                    } else if unicode::is_lead_surrogate(cu) {
-                        flush_pending!();
-                        pending = Some(cu);
-                    } else {
-                        assert!(unicode::is_trail_surrogate(cu));
-                        if let Some(lcu) = pending {
-                            out.push(unicode::compose_surrogates(lcu, cu));
-                            pending = None;
+            Some('\\') => {
+                let escape_site = cursor.clone();
+                match cursor.next() {
+                    Some('"') => push!('"'),
+                    Some('\\') => push!('\\'),
+                    Some('/') => push!('/'),
+                    Some('b') => push!('\x08'),
+                    Some('f') => push!('\x0c'),
+                    Some('n') => push!('\n'),
+                    Some('r') => push!('\r'),
+                    Some('t') => push!('\t'),
+                    Some('u') => {
+                        let cu = parse_four_hex(cursor)?;
+                        if let Some(ch) = char::from_u32(cu as u32) {
+                            push!(ch);
+                        } else if unicode::is_lead_surrogate(cu) {
+                            flush_pending!();
+                            pending = Some(cu);
                         } else {
-                            out.push(char::REPLACEMENT_CHARACTER);
+                            assert!(unicode::is_trail_surrogate(cu));
+                            if let Some(lcu) = pending {
+                                out.push(unicode::compose_surrogates(lcu, cu));
+                                pending = None;
+                            } else {
+                                out.push(char::REPLACEMENT_CHARACTER);
+                            }
                         }
                     }
+                    Some(c) => {
+                        return Err(escape_site.error(
+                            ErrorKind::InvalidEscape,
+                            format!("invalid escape '\\{}'", c),
+                        ))
+                    }
+                    None => return Err(cursor.eof_error("unterminated escape sequence")),
                 }
-                _ => return None,
-            },
-            '\x00'..='\x19' => return None,
-            c => push!(c),
+            }
+            Some(c @ '\x00'..='\x19') => {
+                return Err(char_site.error(
+                    ErrorKind::UnexpectedChar,
+                    format!("control character {:?} not allowed in string", c),
+                ))
+            }
+            Some(c) => push!(c),
+            None => return Err(cursor.eof_error("unterminated string literal")),
         }
     }
 }
 
-fn parse_number(input: &mut Chars) -> Option<f64> {
-    let mut chars = input.clone();
-    if peek(&chars)? == '-' {
-        chars.next();
+/// Scans a number literal starting at `cursor`, returning its lexeme and
+/// the cursor state just past it. Doesn't mutate `cursor` itself, so that
+/// on failure the caller can still report the error at the literal's
+/// start.
+fn scan_number<'a>(cursor: &Cursor<'a>) -> Result<(&'a str, Cursor<'a>), ParseError> {
+    let start_offset = cursor.offset;
+    let mut probe = cursor.clone();
+    if probe.peek() == Some('-') {
+        probe.next();
     }
 
     // Consume integer part.
-    match peek(&chars)? {
-        '0' => {
-            chars.next();
+    match probe.peek() {
+        Some('0') => {
+            probe.next();
+        }
+        Some('1'..='9') => skip_while(&mut probe, |c| c.is_ascii_digit()),
+        Some(c) => {
+            return Err(probe.error(
+                ErrorKind::InvalidNumber,
+                format!("expected digit, found {:?}", c),
+            ))
         }
-        '1'..='9' => skip_while(&mut chars, |c| c.is_ascii_digit()),
-        _ => return None,
+        None => return Err(probe.eof_error("expected digit")),
     }
 
     // Consume fractional part.
-    if peek(&chars) == Some('.') {
-        let mut fchars = chars.clone();
-        fchars.next();
-        if fchars.next().filter(|c| c.is_ascii_digit()).is_some() {
-            skip_while(&mut fchars, |c| c.is_ascii_digit());
-            chars = fchars;
+    if probe.peek() == Some('.') {
+        let mut fprobe = probe.clone();
+        fprobe.next();
+        if fprobe.next().filter(|c| c.is_ascii_digit()).is_some() {
+            skip_while(&mut fprobe, |c| c.is_ascii_digit());
+            probe = fprobe;
         }
     }
 
     // Consume exponential part.
-    if peek(&chars).filter(|&c| c == 'e' || c == 'E').is_some() {
-        let mut echars = chars.clone();
-        echars.next();
-        if peek(&echars).filter(|&c| c == '+' || c == '-').is_some() {
-            echars.next();
+    if probe.peek().filter(|&c| c == 'e' || c == 'E').is_some() {
+        let mut eprobe = probe.clone();
+        eprobe.next();
+        if eprobe.peek().filter(|&c| c == '+' || c == '-').is_some() {
+            eprobe.next();
         }
-        if echars.next().filter(|&c| c.is_ascii_digit()).is_some() {
-            skip_while(&mut echars, |c| c.is_ascii_digit());
-            chars = echars;
+        if eprobe.next().filter(|&c| c.is_ascii_digit()).is_some() {
+            skip_while(&mut eprobe, |c| c.is_ascii_digit());
+            probe = eprobe;
         }
     }
 
-    let consumed_bytes = input.as_str().len() - chars.as_str().len();
-    let num_str = &input.as_str()[..consumed_bytes];
-    *input = chars;
-    Some(num_str.parse().unwrap())
+    let consumed_bytes = probe.offset - start_offset;
+    let text = &cursor.root[start_offset..start_offset + consumed_bytes];
+    Ok((text, probe))
 }
 
-fn parse_keyword(input: &mut Chars, kw: &str, expected: Value) -> Option<Value> {
-    let mut chars = input.clone();
+pub(crate) fn parse_number(cursor: &mut Cursor) -> Result<f64, ParseError> {
+    let (text, probe) = scan_number(cursor)?;
+    let value = text.parse().map_err(|_| {
+        cursor.error(
+            ErrorKind::InvalidNumber,
+            format!("invalid number literal {:?}", text),
+        )
+    })?;
+    *cursor = probe;
+    Ok(value)
+}
+
+/// Like [`parse_number`], but preserves the original lexeme instead of
+/// parsing it into an `f64`, for [`NumberPolicy::Raw`].
+fn parse_number_raw(cursor: &mut Cursor) -> Result<String, ParseError> {
+    let (text, probe) = scan_number(cursor)?;
+    let owned = text.to_string();
+    *cursor = probe;
+    Ok(owned)
+}
+
+pub(crate) fn parse_keyword(
+    cursor: &mut Cursor,
+    kw: &str,
+    expected: Value,
+) -> Result<Value, ParseError> {
+    let mut probe = cursor.clone();
     for c in kw.chars() {
-        if chars.next()? != c {
-            return None;
+        let char_site = probe.clone();
+        match probe.next() {
+            Some(ch) if ch == c => (),
+            Some(ch) => {
+                return Err(char_site.error(
+                    ErrorKind::UnexpectedChar,
+                    format!("expected {:?}, found {:?}", kw, ch),
+                ))
+            }
+            None => return Err(probe.eof_error(format!("expected {:?}", kw))),
         }
     }
-    *input = chars;
-    Some(expected)
+    *cursor = probe;
+    Ok(expected)
 }
 
-fn parse_value(chars: &mut Chars) -> Option<Value> {
-    match peek(chars)? {
-        '{' => parse_object(chars).map(Value::Object),
-        '[' => parse_array(chars).map(Value::Array),
-        '"' => parse_string(chars).map(Value::String),
-        '-' | '0'..='9' => parse_number(chars).map(Value::Number),
-        'f' => parse_keyword(chars, "false", Value::Bool(false)),
-        'n' => parse_keyword(chars, "null", Value::Null),
-        't' => parse_keyword(chars, "true", Value::Bool(true)),
-        _ => None,
+fn parse_value(cursor: &mut Cursor, opts: &ParseOptions) -> Result<Value, ParseError> {
+    match cursor.peek() {
+        Some('{') => parse_object(cursor, opts),
+        Some('[') => parse_array(cursor, opts).map(Value::Array),
+        Some('"') => parse_string(cursor).map(Value::String),
+        Some('-') | Some('0'..='9') => match opts.numbers {
+            NumberPolicy::F64 => parse_number(cursor).map(Value::Number),
+            NumberPolicy::Raw => parse_number_raw(cursor).map(Value::RawNumber),
+        },
+        Some('f') => parse_keyword(cursor, "false", Value::Bool(false)),
+        Some('n') => parse_keyword(cursor, "null", Value::Null),
+        Some('t') => parse_keyword(cursor, "true", Value::Bool(true)),
+        Some(c) => Err(cursor.error(
+            ErrorKind::UnexpectedChar,
+            format!("unexpected character {:?}", c),
+        )),
+        None => Err(cursor.eof_error("expected a value")),
+    }
+}
+
+pub fn parse(s: &str) -> Result<Value, ParseError> {
+    parse_with(s, &ParseOptions::default())
+}
+
+/// Like [`parse`], but with configurable duplicate-key and number
+/// handling; see [`ParseOptions`].
+pub fn parse_with(s: &str, opts: &ParseOptions) -> Result<Value, ParseError> {
+    let mut cursor = Cursor::new(s);
+    skip_while(&mut cursor, is_ws);
+
+    let out = parse_value(&mut cursor, opts)?;
+    skip_while(&mut cursor, is_ws);
+
+    match cursor.peek() {
+        None => Ok(out),
+        Some(c) => Err(cursor.error(
+            ErrorKind::TrailingData,
+            format!("unexpected trailing character {:?}", c),
+        )),
     }
 }
 
-pub fn parse(s: &str) -> Option<Value> {
-    let mut chars = s.chars();
-    skip_while(&mut chars, is_ws);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::DuplicateKeyPolicy;
+
+    fn position(input: &str) -> (usize, usize, usize) {
+        let err = parse(input).unwrap_err();
+        (err.offset, err.line, err.column)
+    }
+
+    #[test]
+    fn unexpected_char_points_at_the_bad_char() {
+        assert_eq!(position("[1, ]"), (4, 1, 5));
+    }
+
+    #[test]
+    fn unexpected_eof_points_at_the_end_of_input() {
+        let err = parse("[1, 2").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::UnexpectedEof);
+        assert_eq!((err.offset, err.line, err.column), (5, 1, 6));
+    }
 
-    let out = parse_value(&mut chars)?;
-    skip_while(&mut chars, is_ws);
+    #[test]
+    fn invalid_number_points_at_the_bad_digit() {
+        let err = parse("[-]").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidNumber);
+        assert_eq!((err.offset, err.line, err.column), (2, 1, 3));
+    }
+
+    #[test]
+    fn invalid_escape_points_at_the_escape_char() {
+        let err = parse("\"ab\\qcd\"").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidEscape);
+        assert_eq!((err.offset, err.line, err.column), (4, 1, 5));
+    }
+
+    #[test]
+    fn trailing_data_points_at_the_first_extra_char() {
+        let err = parse("1 2").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::TrailingData);
+        assert_eq!((err.offset, err.line, err.column), (2, 1, 3));
+    }
+
+    #[test]
+    fn duplicate_key_points_at_the_key_not_the_value() {
+        let opts = ParseOptions::new().duplicate_keys(DuplicateKeyPolicy::Error);
+        let err = parse_with(r#"{"aaaa":1,"aaaa":22222}"#, &opts).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::DuplicateKey);
+        assert_eq!((err.offset, err.line, err.column), (16, 1, 17));
+    }
+
+    #[test]
+    fn position_tracks_lines_and_columns_across_newlines() {
+        let err = parse("[1,\n 2,\nbad]").unwrap_err();
+        assert_eq!(err.line, 3);
+        assert_eq!(err.column, 1);
+    }
 
-    if peek(&chars).is_none() {
-        Some(out)
-    } else {
-        None
+    #[test]
+    fn display_renders_a_caret_under_the_offending_char() {
+        let err = parse("[1, ]").unwrap_err();
+        let rendered = err.to_string();
+        let mut lines = rendered.lines();
+        lines.next(); // "... (line 1, column 5)"
+        assert_eq!(lines.next(), Some("[1, ]"));
+        assert_eq!(lines.next(), Some("    ^"));
     }
 }
@@ -0,0 +1,609 @@
+// SPDX-License-Identifier: MIT OR BlueOak-1.0.0
+
+//! JSONPath-flavored query support over a parsed [`Value`] tree.
+//!
+//! [`compile`] turns a path expression such as `$.store.book[*].title` into
+//! a [`Query`], and [`Query::eval`] walks a tree collecting the matching
+//! nodes. The supported subset covers root `$`, child access (`.name` /
+//! `['name']`), wildcards (`.*` / `[*]`), recursive descent (`..name`),
+//! array index (including negative indices from the end), array slices
+//! (`[start:end:step]`), index unions (`[0,2,5]`), and simple filter
+//! predicates (`[?(@.field <op> literal)]`).
+
+use crate::value::Value;
+use std::cmp::Ordering;
+use std::str::Chars;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Query {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Child(String),
+    Wildcard,
+    RecursiveDescent(String),
+    RecursiveWildcard,
+    Index(i64),
+    Slice(Option<i64>, Option<i64>, i64),
+    Union(Vec<i64>),
+    Filter(Filter),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Filter {
+    field: Vec<String>,
+    op: FilterOp,
+    literal: Literal,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FilterOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Literal {
+    Number(f64),
+    String(String),
+    Bool(bool),
+    Null,
+}
+
+fn peek(chars: &Chars) -> Option<char> {
+    chars.clone().next()
+}
+
+fn next_if(chars: &mut Chars, func: impl FnOnce(char) -> bool) -> Option<char> {
+    match peek(chars) {
+        Some(ch) if func(ch) => Some(chars.next().unwrap()),
+        _ => None,
+    }
+}
+
+fn skip_while(chars: &mut Chars, func: impl Fn(char) -> bool) {
+    while next_if(chars, |c| func(c)).is_some() {}
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+fn parse_name(chars: &mut Chars) -> Option<String> {
+    let mut out = String::new();
+    while let Some(c) = next_if(chars, is_name_char) {
+        out.push(c);
+    }
+    if out.is_empty() {
+        None
+    } else {
+        Some(out)
+    }
+}
+
+fn parse_quoted_name(chars: &mut Chars) -> Option<String> {
+    let quote = next_if(chars, |c| c == '\'' || c == '"')?;
+    let mut out = String::new();
+    loop {
+        match chars.next()? {
+            c if c == quote => return Some(out),
+            '\\' => out.push(chars.next()?),
+            c => out.push(c),
+        }
+    }
+}
+
+fn parse_int(chars: &mut Chars) -> Option<i64> {
+    let mut tmp = String::new();
+    if next_if(chars, |c| c == '-').is_some() {
+        tmp.push('-');
+    }
+    let mut any = false;
+    while let Some(c) = next_if(chars, |c| c.is_ascii_digit()) {
+        tmp.push(c);
+        any = true;
+    }
+    if !any {
+        return None;
+    }
+    tmp.parse().ok()
+}
+
+fn parse_literal(chars: &mut Chars) -> Option<Literal> {
+    match peek(chars)? {
+        '\'' | '"' => parse_quoted_name(chars).map(Literal::String),
+        '-' | '0'..='9' => {
+            let mut tmp = String::new();
+            if next_if(chars, |c| c == '-').is_some() {
+                tmp.push('-');
+            }
+            while let Some(c) = next_if(chars, |c| c.is_ascii_digit() || c == '.') {
+                tmp.push(c);
+            }
+            tmp.parse().ok().map(Literal::Number)
+        }
+        't' => {
+            for c in "true".chars() {
+                if chars.next()? != c {
+                    return None;
+                }
+            }
+            Some(Literal::Bool(true))
+        }
+        'f' => {
+            for c in "false".chars() {
+                if chars.next()? != c {
+                    return None;
+                }
+            }
+            Some(Literal::Bool(false))
+        }
+        'n' => {
+            for c in "null".chars() {
+                if chars.next()? != c {
+                    return None;
+                }
+            }
+            Some(Literal::Null)
+        }
+        _ => None,
+    }
+}
+
+fn parse_op(chars: &mut Chars) -> Option<FilterOp> {
+    let first = chars.next()?;
+    let second = peek(chars);
+    match (first, second) {
+        ('=', Some('=')) => {
+            chars.next();
+            Some(FilterOp::Eq)
+        }
+        ('!', Some('=')) => {
+            chars.next();
+            Some(FilterOp::Ne)
+        }
+        ('<', Some('=')) => {
+            chars.next();
+            Some(FilterOp::Le)
+        }
+        ('>', Some('=')) => {
+            chars.next();
+            Some(FilterOp::Ge)
+        }
+        ('<', _) => Some(FilterOp::Lt),
+        ('>', _) => Some(FilterOp::Gt),
+        _ => None,
+    }
+}
+
+fn parse_filter(chars: &mut Chars) -> Option<Filter> {
+    // `?(@.field <op> literal)`
+    if chars.next()? != '?' {
+        return None;
+    }
+    if chars.next()? != '(' {
+        return None;
+    }
+    skip_while(chars, |c| c == ' ');
+    if chars.next()? != '@' {
+        return None;
+    }
+    let mut field = Vec::new();
+    while peek(chars) == Some('.') {
+        chars.next();
+        field.push(parse_name(chars)?);
+    }
+    if field.is_empty() {
+        return None;
+    }
+    skip_while(chars, |c| c == ' ');
+    let op = parse_op(chars)?;
+    skip_while(chars, |c| c == ' ');
+    let literal = parse_literal(chars)?;
+    skip_while(chars, |c| c == ' ');
+    if chars.next()? != ')' {
+        return None;
+    }
+    Some(Filter { field, op, literal })
+}
+
+fn parse_slice_or_index(chars: &mut Chars) -> Option<Segment> {
+    let mut probe = chars.clone();
+    let start = parse_int(&mut probe);
+    if peek(&probe) == Some(':') {
+        probe.next();
+        let end = parse_int(&mut probe);
+        let step = if peek(&probe) == Some(':') {
+            probe.next();
+            parse_int(&mut probe).unwrap_or(1)
+        } else {
+            1
+        };
+        *chars = probe;
+        return Some(Segment::Slice(start, end, step));
+    }
+    let idx = start?;
+    *chars = probe;
+    Some(Segment::Index(idx))
+}
+
+fn parse_bracket(chars: &mut Chars) -> Option<Segment> {
+    if chars.next()? != '[' {
+        return None;
+    }
+    skip_while(chars, |c| c == ' ');
+    let segment = match peek(chars)? {
+        '*' => {
+            chars.next();
+            Segment::Wildcard
+        }
+        '?' => Segment::Filter(parse_filter(chars)?),
+        '\'' | '"' => Segment::Child(parse_quoted_name(chars)?),
+        _ => {
+            let mut segments = vec![parse_slice_or_index(chars)?];
+            skip_while(chars, |c| c == ' ');
+            while peek(chars) == Some(',') {
+                chars.next();
+                skip_while(chars, |c| c == ' ');
+                segments.push(parse_slice_or_index(chars)?);
+                skip_while(chars, |c| c == ' ');
+            }
+            if segments.len() == 1 {
+                segments.pop().unwrap()
+            } else {
+                let mut indices = Vec::with_capacity(segments.len());
+                for segment in segments {
+                    match segment {
+                        Segment::Index(i) => indices.push(i),
+                        _ => return None,
+                    }
+                }
+                Segment::Union(indices)
+            }
+        }
+    };
+    skip_while(chars, |c| c == ' ');
+    if chars.next()? != ']' {
+        return None;
+    }
+    Some(segment)
+}
+
+/// Compiles a JSONPath expression, returning `None` if it's malformed.
+pub(crate) fn compile(path: &str) -> Option<Query> {
+    let mut chars = path.chars();
+    if peek(&chars) == Some('$') {
+        chars.next();
+    }
+
+    let mut segments = Vec::new();
+    loop {
+        skip_while(&mut chars, |c| c == ' ');
+        match peek(&chars) {
+            None => break,
+            Some('[') => segments.push(parse_bracket(&mut chars)?),
+            Some('.') => {
+                chars.next();
+                let recursive = peek(&chars) == Some('.');
+                if recursive {
+                    chars.next();
+                }
+                match peek(&chars) {
+                    Some('*') => {
+                        chars.next();
+                        segments.push(if recursive {
+                            Segment::RecursiveWildcard
+                        } else {
+                            Segment::Wildcard
+                        });
+                    }
+                    _ => {
+                        let name = parse_name(&mut chars)?;
+                        segments.push(if recursive {
+                            Segment::RecursiveDescent(name)
+                        } else {
+                            Segment::Child(name)
+                        });
+                    }
+                }
+            }
+            Some(_) => return None,
+        }
+    }
+    Some(Query { segments })
+}
+
+impl Query {
+    /// Evaluates this query against `root`. Arrays and ordered objects are
+    /// walked in document order; `Value::Object` entries in `HashMap`
+    /// iteration order.
+    pub(crate) fn eval<'a>(&self, root: &'a Value) -> Vec<&'a Value> {
+        let mut current = vec![root];
+        for segment in &self.segments {
+            current = apply(segment, current);
+        }
+        current
+    }
+}
+
+fn apply<'a>(segment: &Segment, candidates: Vec<&'a Value>) -> Vec<&'a Value> {
+    match segment {
+        Segment::Child(name) => candidates
+            .into_iter()
+            .filter_map(|v| object_get(v, name))
+            .collect(),
+        Segment::Wildcard => candidates.into_iter().flat_map(children).collect(),
+        Segment::RecursiveDescent(name) => candidates
+            .into_iter()
+            .flat_map(|v| descendants(v))
+            .filter_map(|v| object_get(v, name))
+            .collect(),
+        Segment::RecursiveWildcard => candidates
+            .into_iter()
+            .flat_map(|v| descendants(v))
+            .collect(),
+        Segment::Index(i) => candidates
+            .into_iter()
+            .filter_map(|v| match v {
+                Value::Array(arr) => resolve_index(arr.len(), *i).map(|idx| &arr[idx]),
+                _ => None,
+            })
+            .collect(),
+        Segment::Slice(start, end, step) => candidates
+            .into_iter()
+            .flat_map(|v| match v {
+                Value::Array(arr) => slice_indices(arr.len(), *start, *end, *step)
+                    .into_iter()
+                    .map(|idx| &arr[idx])
+                    .collect(),
+                _ => Vec::new(),
+            })
+            .collect(),
+        Segment::Union(indices) => candidates
+            .into_iter()
+            .flat_map(|v| match v {
+                Value::Array(arr) => indices
+                    .iter()
+                    .filter_map(|&i| resolve_index(arr.len(), i))
+                    .map(|idx| &arr[idx])
+                    .collect(),
+                _ => Vec::new(),
+            })
+            .collect(),
+        Segment::Filter(filter) => candidates
+            .into_iter()
+            .flat_map(|v| -> Vec<&'a Value> {
+                children(v).into_iter().filter(|item| filter.matches(item)).collect()
+            })
+            .collect(),
+    }
+}
+
+/// Looks up `name` in `v` if it's an object, regardless of which object
+/// representation [`Value`] is using.
+fn object_get<'a>(v: &'a Value, name: &str) -> Option<&'a Value> {
+    match v {
+        Value::Object(map) => map.get(name),
+        Value::OrderedObject(entries) => entries.iter().find(|(k, _)| k == name).map(|(_, v)| v),
+        _ => None,
+    }
+}
+
+fn children(v: &Value) -> Vec<&Value> {
+    match v {
+        Value::Object(map) => map.values().collect(),
+        Value::OrderedObject(entries) => entries.iter().map(|(_, v)| v).collect(),
+        Value::Array(arr) => arr.iter().collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn descendants(value: &Value) -> Vec<&Value> {
+    let mut out = vec![value];
+    for child in children(value) {
+        out.extend(descendants(child));
+    }
+    out
+}
+
+fn resolve_index(len: usize, i: i64) -> Option<usize> {
+    let idx = if i < 0 { len as i64 + i } else { i };
+    if idx >= 0 && (idx as usize) < len {
+        Some(idx as usize)
+    } else {
+        None
+    }
+}
+
+fn slice_indices(len: usize, start: Option<i64>, end: Option<i64>, step: i64) -> Vec<usize> {
+    if step == 0 || len == 0 {
+        return Vec::new();
+    }
+    let len_i = len as i64;
+    let normalize = |i: i64| if i < 0 { (len_i + i).max(0) } else { i.min(len_i) };
+
+    let mut out = Vec::new();
+    if step > 0 {
+        let start = start.map(normalize).unwrap_or(0).max(0);
+        let end = end.map(normalize).unwrap_or(len_i).min(len_i);
+        let mut i = start;
+        while i < end {
+            out.push(i as usize);
+            i += step;
+        }
+    } else {
+        let start = start.map(normalize).unwrap_or(len_i - 1).min(len_i - 1);
+        let end = end.map(normalize).unwrap_or(-1);
+        let mut i = start;
+        while i > end {
+            if i >= 0 && i < len_i {
+                out.push(i as usize);
+            }
+            i += step;
+        }
+    }
+    out
+}
+
+impl Filter {
+    fn matches(&self, value: &Value) -> bool {
+        match resolve_field(value, &self.field) {
+            Some(target) => compare(target, &self.literal, self.op),
+            None => false,
+        }
+    }
+}
+
+fn resolve_field<'a>(value: &'a Value, field: &[String]) -> Option<&'a Value> {
+    let mut current = value;
+    for name in field {
+        current = object_get(current, name)?;
+    }
+    Some(current)
+}
+
+fn compare(value: &Value, literal: &Literal, op: FilterOp) -> bool {
+    let ordering = match (value, literal) {
+        (Value::Number(a), Literal::Number(b)) => a.partial_cmp(b),
+        // `RawNumber` preserves the original lexeme under `NumberPolicy::Raw`;
+        // parse it back to an `f64` so numeric filters keep working.
+        (Value::RawNumber(a), Literal::Number(b)) => a.parse::<f64>().ok().and_then(|a| a.partial_cmp(b)),
+        (Value::String(a), Literal::String(b)) => Some(a.as_str().cmp(b.as_str())),
+        (Value::Bool(a), Literal::Bool(b)) => Some(a.cmp(b)),
+        (Value::Null, Literal::Null) => Some(Ordering::Equal),
+        _ => None,
+    };
+    match (ordering, op) {
+        (Some(Ordering::Equal), FilterOp::Eq) => true,
+        (Some(o), FilterOp::Ne) => o != Ordering::Equal,
+        (Some(Ordering::Less), FilterOp::Lt) => true,
+        (Some(Ordering::Less) | Some(Ordering::Equal), FilterOp::Le) => true,
+        (Some(Ordering::Greater), FilterOp::Gt) => true,
+        (Some(Ordering::Greater) | Some(Ordering::Equal), FilterOp::Ge) => true,
+        _ => false,
+    }
+}
+#[cfg(test)]
+mod tests {
+    use crate::{parse, parse_with, DuplicateKeyPolicy, NumberPolicy, ParseOptions, Value};
+
+    fn values<'a>(v: &'a Value, path: &str) -> Vec<&'a Value> {
+        v.query(path)
+    }
+
+    // `Value` doesn't implement `PartialEq`, so assert against the scalars
+    // it wraps rather than the values themselves.
+    fn numbers(v: &[&Value]) -> Vec<f64> {
+        v.iter()
+            .map(|v| match v {
+                Value::Number(n) => *n,
+                other => panic!("expected a number, got {:?}", other),
+            })
+            .collect()
+    }
+
+    fn strings<'a>(v: &[&'a Value]) -> Vec<&'a str> {
+        v.iter()
+            .map(|v| match v {
+                Value::String(s) => s.as_str(),
+                other => panic!("expected a string, got {:?}", other),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn slice_basic_and_negative_bounds() {
+        let v = parse("[0,1,2,3,4,5]").unwrap();
+        assert_eq!(numbers(&values(&v, "$[1:3]")), vec![1.0, 2.0]);
+        assert_eq!(numbers(&values(&v, "$[-3:-1]")), vec![3.0, 4.0]);
+        // Open-ended slices default to the start/end of the array.
+        assert_eq!(numbers(&values(&v, "$[:2]")), vec![0.0, 1.0]);
+        assert_eq!(numbers(&values(&v, "$[4:]")), vec![4.0, 5.0]);
+    }
+
+    #[test]
+    fn slice_with_step() {
+        let v = parse("[0,1,2,3,4,5]").unwrap();
+        assert_eq!(numbers(&values(&v, "$[0:6:2]")), vec![0.0, 2.0, 4.0]);
+        // A negative step walks the array backwards.
+        assert_eq!(numbers(&values(&v, "$[5:0:-2]")), vec![5.0, 3.0, 1.0]);
+    }
+
+    #[test]
+    fn slice_out_of_range_yields_no_matches() {
+        let v = parse("[0,1,2]").unwrap();
+        assert!(values(&v, "$[10:20]").is_empty());
+        assert!(values(&v, "$[1:1]").is_empty());
+    }
+
+    #[test]
+    fn union_of_indices() {
+        let v = parse(r#"["a","b","c","d"]"#).unwrap();
+        assert_eq!(strings(&values(&v, "$[0,2]")), vec!["a", "c"]);
+        // Negative indices are resolved the same way a bare index is.
+        assert_eq!(strings(&values(&v, "$[0,-1]")), vec!["a", "d"]);
+    }
+
+    #[test]
+    fn union_with_out_of_range_index_drops_it() {
+        let v = parse(r#"["a","b"]"#).unwrap();
+        assert_eq!(strings(&values(&v, "$[0,5]")), vec!["a"]);
+    }
+
+    #[test]
+    fn negative_index_resolves_from_the_end() {
+        let v = parse("[10,20,30]").unwrap();
+        assert_eq!(numbers(&values(&v, "$[-1]")), vec![30.0]);
+        assert_eq!(numbers(&values(&v, "$[-3]")), vec![10.0]);
+        assert!(values(&v, "$[-4]").is_empty());
+    }
+
+    #[test]
+    fn filter_comparison_operators() {
+        let v = parse(r#"[{"n":1},{"n":2},{"n":3}]"#).unwrap();
+        assert_eq!(values(&v, "$[?(@.n < 2)]").len(), 1);
+        assert_eq!(values(&v, "$[?(@.n >= 2)]").len(), 2);
+        assert_eq!(values(&v, "$[?(@.n != 2)]").len(), 2);
+        assert_eq!(values(&v, "$[?(@.n == 2)]").len(), 1);
+    }
+
+    #[test]
+    fn filter_with_missing_field_does_not_match() {
+        let v = parse(r#"[{"n":1},{"other":2}]"#).unwrap();
+        assert_eq!(values(&v, "$[?(@.n == 1)]").len(), 1);
+    }
+
+    #[test]
+    fn filter_against_raw_number() {
+        // Regression test: `Value::RawNumber` (produced by `NumberPolicy::Raw`)
+        // must still compare correctly against a numeric filter literal.
+        let opts = ParseOptions::new().numbers(NumberPolicy::Raw);
+        let v = parse_with(r#"{"items":[{"n":5},{"n":15}]}"#, &opts).unwrap();
+        let matches = values(&v, "$.items[?(@.n < 10)]");
+        assert_eq!(matches.len(), 1);
+        match matches[0] {
+            Value::Object(map) => match &map["n"] {
+                Value::RawNumber(s) => assert_eq!(s, "5"),
+                other => panic!("expected a raw number, got {:?}", other),
+            },
+            other => panic!("expected an object, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn recursive_descent_over_ordered_object() {
+        // `DuplicateKeyPolicy::KeepAll` produces `Value::OrderedObject`, which
+        // recursive descent and child access need to walk just like `Object`.
+        // `object_get` resolves a key to its first match, so only one `id`
+        // surfaces per object even though `b` has two.
+        let opts = ParseOptions::new().duplicate_keys(DuplicateKeyPolicy::KeepAll);
+        let v = parse_with(r#"{"a":{"id":1},"b":{"id":2,"id":3}}"#, &opts).unwrap();
+        assert_eq!(numbers(&values(&v, "$..id")), vec![1.0, 2.0]);
+    }
+}
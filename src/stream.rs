@@ -0,0 +1,286 @@
+// SPDX-License-Identifier: MIT OR BlueOak-1.0.0
+
+//! Incremental and newline-delimited (NDJSON) JSON parsing, for documents
+//! too large to hold in memory at once or arriving as a network stream.
+//!
+//! [`Parser`] frames complete top-level JSON values out of text fed in
+//! chunks, returning [`Status::Incomplete`] instead of failing outright
+//! when a value is only partially buffered so far. [`parse_many`] and
+//! [`parse_stream`]/[`parse_stream_str`] build on the same framing to walk
+//! a whole document or NDJSON source record-by-record.
+
+use crate::error::ParseError;
+use crate::parse;
+use crate::value::Value;
+use std::io::{self, BufRead, BufReader, Read};
+
+/// The result of feeding more input to a [`Parser`].
+#[derive(Debug)]
+pub enum Status {
+    /// A complete value was framed at the front of the buffer and parsed.
+    /// `Err` means the framed text wasn't valid JSON.
+    Value(Result<Value, ParseError>),
+    /// Not enough input has been fed yet to complete the next value.
+    Incomplete,
+}
+
+/// Frames and parses JSON values out of text fed incrementally.
+///
+/// Whitespace between values is skipped automatically. Once a complete
+/// top-level value (balanced braces/brackets, with string contents
+/// ignored) has accumulated, [`Parser::feed`] parses and returns it,
+/// leaving any trailing input buffered for the next call.
+#[derive(Debug, Default)]
+pub struct Parser {
+    buf: String,
+}
+
+impl Parser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends `chunk` to the internal buffer and tries to frame the next
+    /// complete value.
+    pub fn feed(&mut self, chunk: &str) -> Status {
+        self.buf.push_str(chunk);
+
+        let start = self
+            .buf
+            .find(|c: char| !c.is_whitespace())
+            .unwrap_or(self.buf.len());
+        self.buf.drain(..start);
+
+        match frame_len(&self.buf) {
+            Some(len) => {
+                let value = parse::parse(&self.buf[..len]);
+                self.buf.drain(..len);
+                Status::Value(value)
+            }
+            None => Status::Incomplete,
+        }
+    }
+
+    /// Signals end of input, parsing whatever is left in the buffer.
+    /// Returns `None` if the buffer is empty (aside from whitespace).
+    pub fn finish(self) -> Option<Result<Value, ParseError>> {
+        let rest = self.buf.trim();
+        if rest.is_empty() {
+            None
+        } else {
+            Some(parse::parse(rest))
+        }
+    }
+}
+
+/// Finds the byte length of the first complete top-level JSON value at the
+/// start of `s` (which must not start with whitespace), if one is fully
+/// present.
+fn frame_len(s: &str) -> Option<usize> {
+    let mut chars = s.char_indices();
+    let (_, first) = chars.next()?;
+    if first != '{' && first != '[' {
+        return scalar_frame_len(s);
+    }
+
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in std::iter::once((0, first)).chain(chars) {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' | '[' => depth += 1,
+            '}' | ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + c.len_utf8());
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// A bare string/number/bool/null value at the front of `s` is only known
+/// to be complete once a delimiter (whitespace, since none of these
+/// productions contain unescaped whitespace) shows up after it; until
+/// then more digits/letters could still be on the way.
+fn scalar_frame_len(s: &str) -> Option<usize> {
+    if s.starts_with('"') {
+        let mut escaped = false;
+        for (i, c) in s.char_indices().skip(1) {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                return Some(i + c.len_utf8());
+            }
+        }
+        return None;
+    }
+    s.find(char::is_whitespace)
+}
+
+/// Splits `s` on top-level JSON value boundaries (skipping whitespace
+/// between them) and parses each one independently.
+pub fn parse_many(s: &str) -> impl Iterator<Item = Result<Value, ParseError>> + '_ {
+    let mut rest = s;
+    std::iter::from_fn(move || {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            return None;
+        }
+        let len = frame_len(rest).unwrap_or(rest.len());
+        let (head, tail) = rest.split_at(len);
+        rest = tail;
+        Some(parse::parse(head))
+    })
+}
+
+/// Splits `s` into NDJSON records (one JSON value per line) and parses
+/// each, skipping blank lines.
+pub fn parse_stream_str(s: &str) -> impl Iterator<Item = Result<Value, ParseError>> + '_ {
+    s.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(parse::parse)
+}
+
+/// Reads NDJSON records (one JSON value per line) from `reader`, skipping
+/// blank lines.
+pub fn parse_stream<R: Read>(
+    reader: R,
+) -> impl Iterator<Item = io::Result<Result<Value, ParseError>>> {
+    BufReader::new(reader).lines().filter_map(|line| match line {
+        Ok(line) if line.trim().is_empty() => None,
+        Ok(line) => Some(Ok(parse::parse(line.trim()))),
+        Err(e) => Some(Err(e)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn number(v: &Value) -> f64 {
+        match v {
+            Value::Number(n) => *n,
+            other => panic!("expected a number, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn feed_whole_value_in_one_chunk() {
+        // A bare scalar only frames once a trailing delimiter shows up, so
+        // that e.g. "4" isn't framed before the rest of "42" arrives.
+        let mut parser = Parser::new();
+        match parser.feed("42 ") {
+            Status::Value(Ok(v)) => assert_eq!(number(&v), 42.0),
+            other => panic!("expected a framed value, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn feed_one_byte_at_a_time() {
+        let mut parser = Parser::new();
+        let mut framed = None;
+        for c in "{\"a\":1}".chars() {
+            match parser.feed(&c.to_string()) {
+                Status::Incomplete => {}
+                Status::Value(v) => {
+                    assert!(framed.is_none(), "framed twice");
+                    framed = Some(v);
+                }
+            }
+        }
+        assert!(framed.unwrap().is_ok());
+    }
+
+    #[test]
+    fn feed_across_a_chunk_boundary() {
+        // The chunk boundary falls inside a string; its closing quote and
+        // the object's closing brace only arrive with the second chunk, so
+        // the first chunk alone is not a complete value.
+        let mut parser = Parser::new();
+        assert!(matches!(parser.feed("{\"a\":\"x"), Status::Incomplete));
+        match parser.feed("y\"}") {
+            Status::Value(Ok(_)) => {}
+            other => panic!("expected a framed value, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn feed_leaves_the_next_value_buffered() {
+        let mut parser = Parser::new();
+        match parser.feed("1 2 ") {
+            Status::Value(Ok(v)) => assert_eq!(number(&v), 1.0),
+            other => panic!("expected a framed value, got {:?}", other),
+        }
+        match parser.feed("") {
+            Status::Value(Ok(v)) => assert_eq!(number(&v), 2.0),
+            other => panic!("expected the buffered value, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn finish_on_empty_buffer_is_none() {
+        let parser = Parser::new();
+        assert!(parser.finish().is_none());
+    }
+
+    #[test]
+    fn finish_parses_a_trailing_scalar_with_no_delimiter() {
+        // `feed` can't know "true" is complete without a delimiter after
+        // it (more letters could still be coming); `finish` has no such
+        // doubt once the caller says input has ended.
+        let mut parser = Parser::new();
+        assert!(matches!(parser.feed("true"), Status::Incomplete));
+        match parser.finish() {
+            Some(Ok(_)) => {}
+            other => panic!("expected a parsed value, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_many_splits_concatenated_values() {
+        let values: Vec<_> = parse_many("1 2 3").collect();
+        assert_eq!(values.len(), 3);
+        assert!(values.iter().all(|v| v.is_ok()));
+    }
+
+    #[test]
+    fn parse_stream_str_skips_blank_lines() {
+        let records: Vec<_> = parse_stream_str("{\"a\":1}\n\n{\"a\":2}\n").collect();
+        assert_eq!(records.len(), 2);
+        assert!(records.iter().all(|v| v.is_ok()));
+    }
+
+    #[test]
+    fn parse_stream_str_reports_a_malformed_line() {
+        let records: Vec<_> = parse_stream_str("{\"a\":1}\nnot json\n").collect();
+        assert_eq!(records.len(), 2);
+        assert!(records[0].is_ok());
+        assert!(records[1].is_err());
+    }
+
+    #[test]
+    fn parse_stream_reads_ndjson_from_a_reader() {
+        let records: Vec<_> = parse_stream("{\"a\":1}\n{\"a\":2}\n".as_bytes()).collect();
+        assert_eq!(records.len(), 2);
+        for r in records {
+            assert!(r.unwrap().is_ok());
+        }
+    }
+}
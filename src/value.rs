@@ -1,5 +1,6 @@
 // SPDX-License-Identifier: MIT OR BlueOak-1.0.0
 
+use crate::query;
 use std::collections::HashMap;
 use std::fmt;
 use std::vec::Vec;
@@ -9,8 +10,18 @@ pub enum Value {
     Null,
     Bool(bool),
     Number(f64),
+    /// A number literal preserved as its original lexeme, rather than
+    /// parsed into an `f64`, so large integers round-trip exactly. Only
+    /// produced by [`crate::parse_with`] with
+    /// [`NumberPolicy::Raw`](crate::NumberPolicy::Raw).
+    RawNumber(String),
     String(String),
     Object(HashMap<String, Value>),
+    /// An object that preserves original key order and duplicate entries,
+    /// rather than collapsing them into a `HashMap`. Only produced by
+    /// [`crate::parse_with`] with
+    /// [`DuplicateKeyPolicy::KeepAll`](crate::DuplicateKeyPolicy::KeepAll).
+    OrderedObject(Vec<(String, Value)>),
     Array(Vec<Value>),
 }
 
@@ -27,6 +38,24 @@ impl fmt::Display for Value {
 }
 
 impl Value {
+    /// Selects nodes from this tree using a JSONPath expression, e.g.
+    /// `$.store.book[*].title` or `$..price`. Returns an empty vector if
+    /// the path fails to compile or nothing matches. Array elements and
+    /// [`Value::OrderedObject`] entries are visited in document order;
+    /// entries of a [`Value::Object`] are visited in the `HashMap`'s
+    /// iteration order, so their original document order is not kept.
+    pub fn query(&self, path: &str) -> Vec<&Value> {
+        match query::compile(path) {
+            Some(q) => q.eval(self),
+            None => Vec::new(),
+        }
+    }
+
+    /// Like [`Value::query`], but clones the matches into owned values.
+    pub fn query_into(self, path: &str) -> Vec<Value> {
+        self.query(path).into_iter().cloned().collect()
+    }
+
     fn append_str(s: &str, out: &mut impl fmt::Write) -> fmt::Result {
         out.write_char('"')?;
         for ch in s.chars() {
@@ -56,6 +85,7 @@ impl Value {
                 out.write_str(buf.format_finite(*n))
             }
             Value::Number(_) => out.write_str("null"), // match JavaScript
+            Value::RawNumber(s) => out.write_str(s),
             Value::String(s) => Self::append_str(&s, out),
             Value::Object(o) => {
                 out.write_char('{')?;
@@ -73,6 +103,22 @@ impl Value {
                 }
                 out.write_char('}')
             }
+            Value::OrderedObject(entries) => {
+                out.write_char('{')?;
+                let mut it = entries.iter();
+                if let Some((k, v)) = it.next() {
+                    Self::append_str(k, out)?;
+                    out.write_char(':')?;
+                    v.append(out)?;
+                    for (k, v) in it {
+                        out.write_char(',')?;
+                        Self::append_str(k, out)?;
+                        out.write_char(':')?;
+                        v.append(out)?;
+                    }
+                }
+                out.write_char('}')
+            }
             Value::Array(a) => {
                 out.write_char('[')?;
                 let mut it = a.iter();